@@ -0,0 +1,33 @@
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct PoolConfig {
+    pub db_url: String,
+    #[serde(default)]
+    pub max_connections: Option<u32>,
+    #[serde(default)]
+    pub min_connections: Option<u32>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ManagerConfig {
+    pub shards: Vec<PoolConfig>,
+}
+
+// Lets blob storage point at any S3-compatible backend rather than assuming real AWS,
+// which matters for self-hosted deployments.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct S3Config {
+    pub endpoint_url: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    #[serde(default)]
+    pub force_path_style: bool,
+    // Objects larger than this are uploaded using the multipart API rather than a single
+    // `put_object` call.
+    #[serde(default = "default_multipart_threshold")]
+    pub multipart_threshold: usize,
+}
+
+fn default_multipart_threshold() -> usize {
+    8 * 1024 * 1024
+}
@@ -0,0 +1,136 @@
+use crate::config::S3Config;
+use crate::error::QueueError;
+use crate::manager::QueueManager;
+#[cfg(not(test))]
+use crate::s3::S3Impl;
+#[cfg(test)]
+use crate::s3::MockS3Impl as S3Impl;
+
+// The janitor is responsible for periodically sweeping completed, failed and dead-lettered
+// jobs out of the queue. It runs on a single shard at a time, and reports metrics tagged with
+// that shard's id so operators can see sweep activity per-shard.
+pub struct Janitor {
+    manager: QueueManager,
+    shard_id: String,
+    blob_store: Option<S3Impl>,
+    blob_bucket: Option<String>,
+}
+
+impl Janitor {
+    pub fn new(manager: QueueManager, shard_id: String) -> Self {
+        Self {
+            manager,
+            shard_id,
+            blob_store: None,
+            blob_bucket: None,
+        }
+    }
+
+    // Have the cleanup pass also reap S3 blobs belonging to jobs it sweeps.
+    pub async fn with_blob_gc(mut self, config: &S3Config, bucket: String) -> Self {
+        self.blob_store = Some(S3Impl::from_config(config).await);
+        self.blob_bucket = Some(bucket);
+        self
+    }
+
+    // Runs one cleanup pass: sweeps completed/failed/dead-lettered jobs, and, if blob GC is
+    // configured, deletes any S3 blobs those jobs had referenced.
+    pub async fn cleanup(&self) -> Result<(), QueueError> {
+        let reaped = self.manager.reap_stale_jobs(&self.shard_id).await?;
+
+        metrics::counter!("cyclotron_janitor_jobs_reaped", "shard_id" => self.shard_id.clone())
+            .increment(reaped.len() as u64);
+
+        if let (Some(blob_store), Some(bucket)) = (&self.blob_store, &self.blob_bucket) {
+            self.reap_orphaned_blobs(blob_store, bucket, &reaped).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn reap_orphaned_blobs(
+        &self,
+        blob_store: &S3Impl,
+        bucket: &str,
+        reaped: &[crate::Job],
+    ) -> Result<(), QueueError> {
+        let keys: Vec<String> = reaped.iter().filter_map(|job| job.blob_key.clone()).collect();
+
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        reap_blobs(blob_store, bucket, &keys, &self.shard_id).await
+    }
+}
+
+// Deletes `keys` from `bucket`, warning (but still counting the successes) if some fail
+// to delete. Split out from `Janitor::reap_orphaned_blobs` so it can be unit tested
+// against a mock `S3Impl` without needing a real `QueueManager`.
+async fn reap_blobs(
+    blob_store: &S3Impl,
+    bucket: &str,
+    keys: &[String],
+    shard_id: &str,
+) -> Result<(), QueueError> {
+    let deleted = blob_store.delete_many(bucket, keys).await.map_err(QueueError::from)?;
+
+    if deleted.len() < keys.len() {
+        // The owning job rows are already gone, so these keys are now only
+        // reachable from this log line; an operator needs to delete them by hand.
+        let failed_keys: Vec<&String> = keys.iter().filter(|key| !deleted.contains(*key)).collect();
+        tracing::warn!(
+            shard_id,
+            bucket,
+            failed = keys.len() - deleted.len(),
+            ?failed_keys,
+            "some orphaned blobs failed to delete and are now unreachable from the queue"
+        );
+    }
+
+    metrics::counter!("cyclotron_janitor_blobs_reaped", "shard_id" => shard_id.to_string())
+        .increment(deleted.len() as u64);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `S3Impl::new` is mocked as a static method, which panics unless an expectation is
+    // set on its context, so the mock is built with `default()` instead.
+    fn mock_store() -> S3Impl {
+        S3Impl::default()
+    }
+
+    #[tokio::test]
+    async fn succeeds_when_some_keys_fail_to_delete() {
+        let mut store = mock_store();
+        store
+            .expect_delete_many()
+            .times(1)
+            .returning(|_, _| Ok(vec!["key-1".to_string()]));
+
+        let keys = vec!["key-1".to_string(), "key-2".to_string()];
+
+        let result = reap_blobs(&store, "bucket", &keys, "shard-1").await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn succeeds_when_every_key_deletes() {
+        let mut store = mock_store();
+        store
+            .expect_delete_many()
+            .times(1)
+            .returning(|_, keys| Ok(keys.to_vec()));
+
+        let keys = vec!["key-1".to_string(), "key-2".to_string()];
+
+        let result = reap_blobs(&store, "bucket", &keys, "shard-1").await;
+
+        assert!(result.is_ok());
+    }
+}
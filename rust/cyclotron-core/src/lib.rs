@@ -30,6 +30,11 @@ pub use janitor::Janitor;
 mod config;
 pub use config::ManagerConfig;
 pub use config::PoolConfig;
+pub use config::S3Config;
+
+// Blob storage
+mod s3;
+pub use s3::S3Impl;
 
 // The shard id is a fixed value that is set by the janitor when it starts up.
 // Workers may use this value when reporting metrics. The `Worker` struct provides
@@ -0,0 +1,385 @@
+use std::time::Duration;
+
+use aws_config::BehaviorVersion;
+use aws_sdk_s3::{
+    config::{Credentials, Region},
+    presigning::PresigningConfig,
+    primitives::ByteStream,
+    types::{CompletedMultipartUpload, CompletedPart, Delete, ObjectIdentifier},
+    Client as S3Client, Error as S3Error,
+};
+#[cfg(test)]
+use mockall::automock;
+
+use futures::stream::{self, StreamExt, TryStreamExt};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::config::S3Config;
+use crate::error::QueueError;
+
+// Default for `S3Config::multipart_threshold`; objects larger than this are uploaded using
+// the multipart API rather than a single `put_object` call.
+const DEFAULT_MULTIPART_THRESHOLD: usize = 8 * 1024 * 1024;
+// S3 requires all parts but the last to be at least 5 MiB.
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+// How many parts we'll upload concurrently for a single multipart upload.
+const MAX_CONCURRENT_PARTS: usize = 4;
+
+// We wrap the s3 client to allow us to use mocks for testing. We only expose the functionality
+// we need.
+#[allow(dead_code)]
+pub struct S3Impl {
+    inner: S3Client,
+    multipart_threshold: usize,
+}
+
+#[cfg_attr(test, automock)]
+impl S3Impl {
+    #[allow(dead_code)]
+    pub fn new(inner: S3Client) -> Self {
+        Self {
+            inner,
+            multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub async fn from_config(config: &S3Config) -> Self {
+        let credentials = Credentials::new(
+            &config.access_key_id,
+            &config.secret_access_key,
+            None,
+            None,
+            "s3-config",
+        );
+
+        let sdk_config = aws_config::defaults(BehaviorVersion::latest())
+            .region(Region::new(config.region.clone()))
+            .endpoint_url(&config.endpoint_url)
+            .credentials_provider(credentials)
+            .load()
+            .await;
+
+        let inner = S3Client::from_conf(
+            aws_sdk_s3::config::Builder::from(&sdk_config)
+                .force_path_style(config.force_path_style)
+                .build(),
+        );
+
+        Self {
+            inner,
+            multipart_threshold: config.multipart_threshold,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub async fn get(&self, bucket: &str, key: &str) -> Result<(Vec<u8>, Option<String>), QueueError> {
+        let res = self.inner.get_object().bucket(bucket).key(key).send().await;
+
+        if let Ok(res) = res {
+            let etag = res.e_tag.clone();
+            let data = res.body.collect().await.map_err(S3Error::from)?;
+            return Ok((data.to_vec(), etag));
+        }
+
+        // Note that we're not handling the "object not found" case here, because if we
+        // got a key from the DB, we should have the object in S3
+        Err(S3Error::from(res.unwrap_err()).into())
+    }
+
+    // Conditional fetch for callers holding a cached copy: if `etag` still matches, we
+    // skip re-transferring the bytes entirely.
+    #[allow(dead_code)]
+    pub async fn get_if_none_match(
+        &self,
+        bucket: &str,
+        key: &str,
+        etag: &str,
+    ) -> Result<Option<(Vec<u8>, Option<String>)>, QueueError> {
+        let res = self
+            .inner
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .if_none_match(etag)
+            .send()
+            .await;
+
+        match res {
+            Ok(res) => {
+                let etag = res.e_tag.clone();
+                let data = res.body.collect().await.map_err(S3Error::from)?;
+                Ok(Some((data.to_vec(), etag)))
+            }
+            Err(e) if is_not_modified(&e) => Ok(None),
+            Err(e) => Err(S3Error::from(e).into()),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub async fn get_streaming(
+        &self,
+        bucket: &str,
+        key: &str,
+        mut writer: impl AsyncWrite + Unpin,
+    ) -> Result<(), QueueError> {
+        let res = self
+            .inner
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(S3Error::from)?;
+
+        let mut body = res.body;
+        while let Some(chunk) = body.try_next().await.map_err(S3Error::from)? {
+            writer.write_all(&chunk).await.map_err(QueueError::from)?;
+        }
+        writer.flush().await.map_err(QueueError::from)?;
+
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub async fn put(&self, bucket: &str, key: &str, data: Vec<u8>) -> Result<(), QueueError> {
+        if data.len() > self.multipart_threshold {
+            return self.put_multipart(bucket, key, data).await;
+        }
+
+        self.inner
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(|e| S3Error::from(e).into())
+            .map(|_| ()) // We don't care about the result as long as it's success
+    }
+
+    // Uploads a large payload as a series of parts, up to `MAX_CONCURRENT_PARTS` at a time.
+    // The whole payload is still held in memory, and every part is copied out of it before
+    // the first upload starts. Aborts the upload on any part failure so we don't leave an
+    // incomplete upload accruing storage charges.
+    async fn put_multipart(&self, bucket: &str, key: &str, data: Vec<u8>) -> Result<(), QueueError> {
+        let create_res = self
+            .inner
+            .create_multipart_upload()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(S3Error::from)?;
+
+        let upload_id = create_res.upload_id.ok_or_else(|| {
+            QueueError::from(S3Error::from("create_multipart_upload returned no upload_id".to_string()))
+        })?;
+
+        let uploads = stream::iter(split_into_parts(&data).into_iter().enumerate().map(|(i, part)| {
+            let part_number = i as i32 + 1;
+            async move {
+                self.inner
+                    .upload_part()
+                    .bucket(bucket)
+                    .key(key)
+                    .upload_id(&upload_id)
+                    .part_number(part_number)
+                    .body(ByteStream::from(part))
+                    .send()
+                    .await
+                    .map_err(S3Error::from)
+                    .map(|res| {
+                        CompletedPart::builder()
+                            .part_number(part_number)
+                            .set_e_tag(res.e_tag)
+                            .build()
+                    })
+            }
+        }))
+        .buffered(MAX_CONCURRENT_PARTS)
+        .collect::<Vec<_>>()
+        .await;
+
+        let completed_parts: Result<Vec<CompletedPart>, S3Error> = uploads.into_iter().collect();
+        let completed_parts = match completed_parts {
+            Ok(parts) => parts,
+            Err(e) => {
+                if let Err(abort_err) = self.abort_multipart(bucket, key, &upload_id).await {
+                    tracing::warn!(bucket, key, upload_id = %upload_id, error = %e, "part upload failed, and the abort that followed also failed");
+                    return Err(abort_err);
+                }
+                return Err(e.into());
+            }
+        };
+
+        let complete_res = self
+            .inner
+            .complete_multipart_upload()
+            .bucket(bucket)
+            .key(key)
+            .upload_id(&upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(completed_parts))
+                    .build(),
+            )
+            .send()
+            .await;
+
+        if let Err(e) = complete_res {
+            if let Err(abort_err) = self.abort_multipart(bucket, key, &upload_id).await {
+                tracing::warn!(bucket, key, upload_id = %upload_id, error = %e, "complete_multipart_upload failed, and the abort that followed also failed");
+                return Err(abort_err);
+            }
+            return Err(S3Error::from(e).into());
+        }
+
+        Ok(())
+    }
+
+    async fn abort_multipart(&self, bucket: &str, key: &str, upload_id: &str) -> Result<(), QueueError> {
+        self.inner
+            .abort_multipart_upload()
+            .bucket(bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .send()
+            .await
+            .map_err(S3Error::from)?;
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub async fn delete(&self, bucket: &str, key: &str) -> Result<(), QueueError> {
+        self.inner
+            .delete_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| S3Error::from(e).into())
+            .map(|_| ())
+    }
+
+    // Batches deletes into as few `delete_objects` calls as possible (S3 caps a single
+    // request at 1000 keys). Returns the subset of keys that actually got deleted, since
+    // `delete_objects` can come back 200 OK with some keys individually failed.
+    #[allow(dead_code)]
+    pub async fn delete_many(&self, bucket: &str, keys: &[String]) -> Result<Vec<String>, QueueError> {
+        let mut deleted = Vec::with_capacity(keys.len());
+
+        for chunk in keys.chunks(1000) {
+            let objects = chunk
+                .iter()
+                .map(|key| ObjectIdentifier::builder().key(key).build())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(S3Error::from)?;
+
+            let res = self
+                .inner
+                .delete_objects()
+                .bucket(bucket)
+                .delete(Delete::builder().set_objects(Some(objects)).build().map_err(S3Error::from)?)
+                .send()
+                .await
+                .map_err(S3Error::from)?;
+
+            let failed_keys: std::collections::HashSet<_> = res
+                .errors()
+                .iter()
+                .filter_map(|e| e.key().map(str::to_string))
+                .collect();
+
+            deleted.extend(exclude_failed(chunk, &failed_keys));
+        }
+
+        Ok(deleted)
+    }
+
+    #[allow(dead_code)]
+    pub async fn presign_get(
+        &self,
+        bucket: &str,
+        key: &str,
+        expires_in: Duration,
+    ) -> Result<String, QueueError> {
+        let presigning_config = PresigningConfig::expires_in(expires_in)
+            .map_err(|e| QueueError::from(S3Error::from(e.to_string())))?;
+
+        let presigned = self
+            .inner
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .presigned(presigning_config)
+            .await
+            .map_err(S3Error::from)?;
+
+        Ok(presigned.uri().to_string())
+    }
+}
+
+fn split_into_parts(data: &[u8]) -> Vec<Vec<u8>> {
+    data.chunks(MIN_PART_SIZE).map(|chunk| chunk.to_vec()).collect()
+}
+
+// Returns the subset of `keys` not present in `failed_keys`, preserving order. Split out
+// from `delete_many` so the partial-failure bookkeeping is testable without a real
+// `delete_objects` call.
+fn exclude_failed(keys: &[String], failed_keys: &std::collections::HashSet<String>) -> Vec<String> {
+    keys.iter().filter(|key| !failed_keys.contains(*key)).cloned().collect()
+}
+
+// `if_none_match` comes back as an SDK error on a 304, not a successful response, so we
+// inspect the raw status to tell "not modified" apart from a real failure.
+fn is_not_modified(err: &aws_sdk_s3::error::SdkError<aws_sdk_s3::operation::get_object::GetObjectError>) -> bool {
+    err.raw_response()
+        .map(|res| res.status().as_u16() == 304)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_a_trailing_remainder_into_a_smaller_last_part() {
+        let data = vec![0u8; MIN_PART_SIZE * 2 + 1];
+        let parts = split_into_parts(&data);
+
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].len(), MIN_PART_SIZE);
+        assert_eq!(parts[1].len(), MIN_PART_SIZE);
+        assert_eq!(parts[2].len(), 1);
+    }
+
+    #[test]
+    fn splits_an_exact_multiple_without_an_empty_trailing_part() {
+        let data = vec![0u8; MIN_PART_SIZE * 2];
+        let parts = split_into_parts(&data);
+
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].len(), MIN_PART_SIZE);
+        assert_eq!(parts[1].len(), MIN_PART_SIZE);
+    }
+
+    #[test]
+    fn excludes_keys_that_failed_to_delete() {
+        let keys = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let failed_keys = std::collections::HashSet::from(["b".to_string()]);
+
+        let deleted = exclude_failed(&keys, &failed_keys);
+
+        assert_eq!(deleted, vec!["a".to_string(), "c".to_string()]);
+    }
+
+    #[test]
+    fn keeps_all_keys_when_none_failed() {
+        let keys = vec!["a".to_string(), "b".to_string()];
+
+        let deleted = exclude_failed(&keys, &std::collections::HashSet::new());
+
+        assert_eq!(deleted, keys);
+    }
+}
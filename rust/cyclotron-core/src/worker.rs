@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::io::AsyncWrite;
+
+#[cfg(not(test))]
+use crate::s3::S3Impl;
+#[cfg(test)]
+use crate::s3::MockS3Impl as S3Impl;
+
+use crate::error::QueueError;
+use crate::manager::QueueManager;
+use crate::Job;
+
+// Keyed by (bucket, key) only, not etag, so a refresh overwrites the old entry instead
+// of growing the map forever.
+type BlobCacheKey = (String, String);
+
+struct CachedBlob {
+    etag: String,
+    data: Vec<u8>,
+}
+
+#[derive(Default)]
+struct BlobCache {
+    entries: Mutex<HashMap<BlobCacheKey, CachedBlob>>,
+}
+
+impl BlobCache {
+    fn cached_etag(&self, key: &BlobCacheKey) -> Option<String> {
+        self.entries.lock().unwrap().get(key).map(|c| c.etag.clone())
+    }
+
+    fn cached_data(&self, key: &BlobCacheKey) -> Vec<u8> {
+        self.entries.lock().unwrap()[key].data.clone()
+    }
+
+    fn insert(&self, key: BlobCacheKey, etag: String, data: Vec<u8>) {
+        self.entries.lock().unwrap().insert(key, CachedBlob { etag, data });
+    }
+}
+
+// Reuses a cached blob when S3 confirms it hasn't changed, instead of re-downloading it
+// on every retry. Split out from `Worker::fetch_payload` so it can be unit tested against
+// a mock `S3Impl` without needing a real `QueueManager`.
+async fn fetch_with_cache(
+    store: &S3Impl,
+    cache: &BlobCache,
+    bucket: &str,
+    key: &str,
+) -> Result<Vec<u8>, QueueError> {
+    let cache_key = (bucket.to_string(), key.to_string());
+
+    if let Some(etag) = cache.cached_etag(&cache_key) {
+        return match store.get_if_none_match(bucket, key, &etag).await.map_err(QueueError::from)? {
+            Some((data, new_etag)) => {
+                cache.insert(cache_key, new_etag.unwrap_or_default(), data.clone());
+                Ok(data)
+            }
+            None => Ok(cache.cached_data(&cache_key)),
+        };
+    }
+
+    let (data, etag) = store.get(bucket, key).await.map_err(QueueError::from)?;
+    cache.insert(cache_key, etag.unwrap_or_default(), data.clone());
+    Ok(data)
+}
+
+// A worker pulls jobs off the queue and hands them to a caller to execute. Some jobs carry
+// a payload too large to store inline in postgres; those jobs instead carry a `blob_key`
+// pointing at the object in S3, which the worker fetches on the caller's behalf.
+pub struct Worker {
+    manager: QueueManager,
+    blob_store: Option<S3Impl>,
+    blob_bucket: Option<String>,
+    blob_cache: BlobCache,
+}
+
+impl Worker {
+    pub fn new(manager: QueueManager) -> Self {
+        Self {
+            manager,
+            blob_store: None,
+            blob_bucket: None,
+            blob_cache: BlobCache::default(),
+        }
+    }
+
+    pub fn with_blob_store(mut self, blob_store: S3Impl, bucket: String) -> Self {
+        self.blob_store = Some(blob_store);
+        self.blob_bucket = Some(bucket);
+        self
+    }
+
+    pub async fn fetch_payload(&self, job: &Job) -> Result<Vec<u8>, QueueError> {
+        let (blob_store, bucket) = self.blob_store_and_bucket(job)?;
+        let key = job
+            .blob_key
+            .as_ref()
+            .ok_or(QueueError::NoBlobForJob(job.id))?;
+
+        fetch_with_cache(blob_store, &self.blob_cache, bucket, key).await
+    }
+
+    // Streams a job's blob payload straight to `writer` instead of buffering it.
+    pub async fn stream_payload(
+        &self,
+        job: &Job,
+        writer: impl AsyncWrite + Unpin,
+    ) -> Result<(), QueueError> {
+        let (blob_store, bucket) = self.blob_store_and_bucket(job)?;
+
+        let key = job
+            .blob_key
+            .as_ref()
+            .ok_or(QueueError::NoBlobForJob(job.id))?;
+
+        blob_store
+            .get_streaming(bucket, key, writer)
+            .await
+            .map_err(QueueError::from)
+    }
+
+    // Hands back a presigned URL for a job's blob instead of the bytes themselves.
+    pub async fn presign_payload(
+        &self,
+        job: &Job,
+        expires_in: Duration,
+    ) -> Result<String, QueueError> {
+        let (blob_store, bucket) = self.blob_store_and_bucket(job)?;
+        let key = job
+            .blob_key
+            .as_ref()
+            .ok_or(QueueError::NoBlobForJob(job.id))?;
+
+        blob_store
+            .presign_get(bucket, key, expires_in)
+            .await
+            .map_err(QueueError::from)
+    }
+
+    fn blob_store_and_bucket(&self, job: &Job) -> Result<(&S3Impl, &str), QueueError> {
+        if job.blob_key.is_none() {
+            return Err(QueueError::NoBlobForJob(job.id));
+        }
+
+        match (&self.blob_store, &self.blob_bucket) {
+            (Some(store), Some(bucket)) => Ok((store, bucket)),
+            _ => Err(QueueError::BlobStoreNotConfigured),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `S3Impl::new` is mocked as a static method, which panics unless an expectation is
+    // set on its context, so the mock is built with `default()` instead.
+    fn mock_store() -> S3Impl {
+        S3Impl::default()
+    }
+
+    #[tokio::test]
+    async fn cache_miss_fetches_and_populates_cache() {
+        let mut store = mock_store();
+        store
+            .expect_get()
+            .times(1)
+            .returning(|_, _| Ok((b"hello".to_vec(), Some("etag-1".to_string()))));
+
+        let cache = BlobCache::default();
+        let data = fetch_with_cache(&store, &cache, "bucket", "key").await.unwrap();
+
+        assert_eq!(data, b"hello");
+        assert_eq!(
+            cache.cached_etag(&("bucket".to_string(), "key".to_string())),
+            Some("etag-1".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn cache_hit_reuses_bytes_without_a_fresh_get() {
+        let mut store = mock_store();
+        store.expect_get().times(0);
+        store
+            .expect_get_if_none_match()
+            .times(1)
+            .returning(|_, _, _| Ok(None));
+
+        let cache = BlobCache::default();
+        cache.insert(("bucket".to_string(), "key".to_string()), "etag-1".to_string(), b"hello".to_vec());
+
+        let data = fetch_with_cache(&store, &cache, "bucket", "key").await.unwrap();
+
+        assert_eq!(data, b"hello");
+    }
+
+    #[tokio::test]
+    async fn cache_refreshes_on_a_changed_etag() {
+        let mut store = mock_store();
+        store
+            .expect_get_if_none_match()
+            .times(1)
+            .returning(|_, _, _| Ok(Some((b"new bytes".to_vec(), Some("etag-2".to_string())))));
+
+        let cache = BlobCache::default();
+        cache.insert(("bucket".to_string(), "key".to_string()), "etag-1".to_string(), b"old bytes".to_vec());
+
+        let data = fetch_with_cache(&store, &cache, "bucket", "key").await.unwrap();
+
+        assert_eq!(data, b"new bytes");
+        assert_eq!(
+            cache.cached_etag(&("bucket".to_string(), "key".to_string())),
+            Some("etag-2".to_string())
+        );
+    }
+}